@@ -0,0 +1,86 @@
+use std::collections::{HashMap, HashSet};
+
+use dist_sys_rs::message::{EntityId, ErrorCode, MsgEnvelope, MsgPayload};
+use dist_sys_rs::node::{Node, Runner};
+
+#[derive(Default)]
+struct Broadcast {
+    seen: HashSet<u64>,
+    neighbors: Vec<EntityId>,
+    /// Gossiped values that haven't been acked yet, keyed by the `msg_id`
+    /// they were last sent under so a matching `broadcast_ok` can clear them.
+    pending: HashMap<(EntityId, usize), u64>,
+}
+
+impl Broadcast {
+    async fn gossip(&mut self, runner: &Runner<Self>, message: u64, exclude: Option<&EntityId>) {
+        for neighbor in self.neighbors.clone() {
+            if Some(&neighbor) != exclude {
+                self.gossip_one(runner, neighbor, message).await;
+            }
+        }
+    }
+
+    async fn gossip_one(&mut self, runner: &Runner<Self>, dest: EntityId, message: u64) {
+        match runner.send(dest.clone(), MsgPayload::Broadcast { message }).await {
+            Ok(msg_id) => {
+                self.pending.insert((dest, msg_id), message);
+            }
+            Err(e) => eprintln!("failed to gossip to {dest}: {e}"),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Node for Broadcast {
+    async fn handle(&mut self, runner: &Runner<Self>, req: MsgEnvelope) {
+        match req.body.payload {
+            MsgPayload::Topology { ref topology } => {
+                self.neighbors = topology.get(&runner.node_id()).cloned().unwrap_or_default();
+                runner
+                    .reply(&req, MsgPayload::TopologyOk)
+                    .await
+                    .expect("failed to write reply");
+            }
+            MsgPayload::Broadcast { message } => {
+                runner
+                    .reply(&req, MsgPayload::BroadcastOk)
+                    .await
+                    .expect("failed to write reply");
+                if self.seen.insert(message) {
+                    self.gossip(runner, message, Some(&req.src)).await;
+                }
+            }
+            MsgPayload::BroadcastOk => {
+                if let Some(msg_id) = req.body.in_reply_to {
+                    self.pending.remove(&(req.src, msg_id));
+                }
+            }
+            MsgPayload::Read => {
+                let messages = self.seen.iter().copied().collect();
+                runner
+                    .reply(&req, MsgPayload::ReadOk { messages })
+                    .await
+                    .expect("failed to write reply");
+            }
+            _ => {
+                let error = MsgPayload::Error {
+                    code: ErrorCode::NotSupported,
+                    text: "this node only understands the broadcast workload".to_string(),
+                };
+                runner.reply(&req, error).await.expect("failed to write reply");
+            }
+        }
+    }
+
+    async fn on_tick(&mut self, runner: &Runner<Self>) {
+        for ((dest, _acked_msg_id), message) in std::mem::take(&mut self.pending) {
+            self.gossip_one(runner, dest, message).await;
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    Runner::new(Broadcast::default()).run().await
+}
@@ -0,0 +1,30 @@
+use dist_sys_rs::message::{ErrorCode, MsgEnvelope, MsgPayload};
+use dist_sys_rs::node::{Node, Runner};
+
+struct Echo;
+
+#[async_trait::async_trait]
+impl Node for Echo {
+    async fn handle(&mut self, runner: &Runner<Self>, req: MsgEnvelope) {
+        match req.body.payload {
+            MsgPayload::Echo { ref echo } => {
+                let reply = MsgPayload::EchoOk {
+                    echo: echo.clone(),
+                };
+                runner.reply(&req, reply).await.expect("failed to write reply");
+            }
+            _ => {
+                let error = MsgPayload::Error {
+                    code: ErrorCode::NotSupported,
+                    text: "this node only understands echo".to_string(),
+                };
+                runner.reply(&req, error).await.expect("failed to write reply");
+            }
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    Runner::new(Echo).run().await
+}
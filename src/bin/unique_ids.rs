@@ -0,0 +1,51 @@
+use std::time::SystemTime;
+
+use rand::{RngCore, SeedableRng, rngs::StdRng};
+
+use dist_sys_rs::message::{ErrorCode, MsgEnvelope, MsgPayload};
+use dist_sys_rs::node::{Node, Runner};
+
+#[derive(Default)]
+struct UniqueIds {
+    rng: Option<StdRng>,
+}
+
+#[async_trait::async_trait]
+impl Node for UniqueIds {
+    async fn handle(&mut self, runner: &Runner<Self>, req: MsgEnvelope) {
+        match req.body.payload {
+            MsgPayload::Generate => {
+                let node_id: u64 = runner.node_id().into();
+                let rng = self
+                    .rng
+                    .get_or_insert_with(|| StdRng::seed_from_u64(node_id));
+
+                let now = SystemTime::now()
+                    .duration_since(SystemTime::UNIX_EPOCH)
+                    .expect("system time is before UNIX_EPOCH")
+                    .as_secs();
+                let random_part: u32 = rng.next_u32();
+                let id = (now & 0xFFFFFFFF) << 32
+                    | (node_id & 0x3) << 30
+                    | (random_part as u64 & 0xFFFFFFFC);
+
+                runner
+                    .reply(&req, MsgPayload::GenerateOk { id })
+                    .await
+                    .expect("failed to write reply");
+            }
+            _ => {
+                let error = MsgPayload::Error {
+                    code: ErrorCode::NotSupported,
+                    text: "this node only understands generate".to_string(),
+                };
+                runner.reply(&req, error).await.expect("failed to write reply");
+            }
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    Runner::new(UniqueIds::default()).run().await
+}
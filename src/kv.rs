@@ -0,0 +1,118 @@
+use std::fmt::{self, Display};
+use std::time::Duration;
+
+use serde_json::Value;
+
+use crate::message::{EntityId, ErrorCode, KvPayload};
+use crate::node::{Node, Runner};
+
+/// How long to wait for a storage service to reply before giving up.
+const RPC_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A handle to one of Maelstrom's built-in storage services (`seq-kv`,
+/// `lin-kv`, `lww-kv`), reached as an ordinary node over the message layer.
+pub struct Kv {
+    addr: EntityId,
+}
+
+impl Kv {
+    /// The sequentially-consistent store.
+    pub fn seq() -> Self {
+        Kv::at("seq-kv")
+    }
+
+    /// The linearizable store.
+    pub fn lin() -> Self {
+        Kv::at("lin-kv")
+    }
+
+    /// The last-write-wins store.
+    pub fn lww() -> Self {
+        Kv::at("lww-kv")
+    }
+
+    fn at(name: &str) -> Self {
+        Kv {
+            addr: EntityId::Service(name.to_string()),
+        }
+    }
+
+    pub async fn read<N: Node + 'static>(&self, runner: &Runner<N>, key: Value) -> Result<Option<Value>, KvError> {
+        match self.rpc(runner, KvPayload::Read { key }).await? {
+            KvPayload::ReadOk { value } => Ok(Some(value)),
+            KvPayload::Error {
+                code: ErrorCode::KeyDoesNotExist,
+                ..
+            } => Ok(None),
+            KvPayload::Error { code, text } => Err(KvError::Service { code, text }),
+            other => panic!("unexpected reply to read: {other:?}"),
+        }
+    }
+
+    pub async fn write<N: Node + 'static>(&self, runner: &Runner<N>, key: Value, value: Value) -> Result<(), KvError> {
+        match self.rpc(runner, KvPayload::Write { key, value }).await? {
+            KvPayload::WriteOk => Ok(()),
+            KvPayload::Error { code, text } => Err(KvError::Service { code, text }),
+            other => panic!("unexpected reply to write: {other:?}"),
+        }
+    }
+
+    pub async fn cas<N: Node + 'static>(
+        &self,
+        runner: &Runner<N>,
+        key: Value,
+        from: Value,
+        to: Value,
+        create_if_not_exists: bool,
+    ) -> Result<(), KvError> {
+        match self
+            .rpc(
+                runner,
+                KvPayload::Cas {
+                    key,
+                    from,
+                    to,
+                    create_if_not_exists: Some(create_if_not_exists),
+                },
+            )
+            .await?
+        {
+            KvPayload::CasOk => Ok(()),
+            KvPayload::Error { code, text } => Err(KvError::Service { code, text }),
+            other => panic!("unexpected reply to cas: {other:?}"),
+        }
+    }
+
+    /// Sends `payload` to the storage service and awaits its reply.
+    async fn rpc<N: Node + 'static>(&self, runner: &Runner<N>, payload: KvPayload) -> Result<KvPayload, KvError> {
+        runner
+            .rpc(self.addr.clone(), payload, RPC_TIMEOUT)
+            .await
+            .map_err(|code| match code {
+                ErrorCode::Timeout => KvError::Timeout,
+                code => KvError::Service {
+                    code,
+                    text: "rpc failed".to_string(),
+                },
+            })
+    }
+}
+
+#[derive(Debug)]
+pub enum KvError {
+    /// No reply arrived before the rpc timeout elapsed.
+    Timeout,
+    /// The storage service rejected the request, e.g. a failed `cas`.
+    Service { code: ErrorCode, text: String },
+}
+
+impl Display for KvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KvError::Timeout => write!(f, "kv rpc timed out"),
+            KvError::Service { code, text } => write!(f, "kv rpc rejected ({code:?}): {text}"),
+        }
+    }
+}
+
+impl std::error::Error for KvError {}
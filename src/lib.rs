@@ -0,0 +1,3 @@
+pub mod kv;
+pub mod message;
+pub mod node;
@@ -1,11 +1,15 @@
 use std::{fmt::Display, str::FromStr};
 
 use serde::{Deserialize, Serialize, de::Visitor};
+use serde_repr::{Deserialize_repr, Serialize_repr};
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum EntityId {
     Client(usize),
     Node(usize),
+    /// A Maelstrom storage service such as `seq-kv` or `lin-kv`, addressed by
+    /// name rather than by a `<prefix><number>` id.
+    Service(String),
 }
 
 impl Display for EntityId {
@@ -13,6 +17,7 @@ impl Display for EntityId {
         match self {
             EntityId::Client(id) => write!(f, "c{}", id),
             EntityId::Node(id) => write!(f, "n{}", id),
+            EntityId::Service(name) => write!(f, "{}", name),
         }
     }
 }
@@ -36,14 +41,10 @@ impl FromStr for EntityId {
 
         let (prefix, id) = s.split_at_checked(1).ok_or("Invalid EntityId format")?;
 
-        let id = id
-            .parse::<usize>()
-            .map_err(|_| "Invalid number for EntityId")?;
-
-        match prefix {
-            "c" => Ok(EntityId::Client(id)),
-            "n" => Ok(EntityId::Node(id)),
-            _ => Err("Invalid EntityId prefix"),
+        match (prefix, id.parse::<usize>()) {
+            ("c", Ok(id)) => Ok(EntityId::Client(id)),
+            ("n", Ok(id)) => Ok(EntityId::Node(id)),
+            _ => Ok(EntityId::Service(s.to_string())),
         }
     }
 }
@@ -54,7 +55,7 @@ impl<'de> Visitor<'de> for EntityIdVisitor {
     type Value = EntityId;
 
     fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(formatter, r#"a string like "c42" or "n7""#)
+        write!(formatter, r#"a string like "c42", "n7", or "seq-kv""#)
     }
 
     fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
@@ -79,20 +80,21 @@ impl From<EntityId> for u64 {
         match entity_id {
             EntityId::Client(id) => id as u64,
             EntityId::Node(id) => id as u64,
+            EntityId::Service(name) => panic!("service id {name:?} has no numeric id"),
         }
     }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
-pub enum MsgPayload<'p> {
+pub enum MsgPayload {
     Init {
         node_id: EntityId,
         node_ids: Vec<EntityId>,
     },
     InitOk,
     Echo {
-        echo: &'p str,
+        echo: String,
     },
     EchoOk {
         echo: String,
@@ -101,31 +103,117 @@ pub enum MsgPayload<'p> {
     GenerateOk {
         id: u64,
     },
+    Error {
+        code: ErrorCode,
+        text: String,
+    },
+    Topology {
+        topology: std::collections::HashMap<EntityId, Vec<EntityId>>,
+    },
+    TopologyOk,
+    Broadcast {
+        message: u64,
+    },
+    BroadcastOk,
+    Read,
+    ReadOk {
+        messages: Vec<u64>,
+    },
 }
 
+/// The wire protocol spoken by Maelstrom's built-in storage services
+/// (`seq-kv`, `lin-kv`, `lww-kv`), reached through [`crate::kv::Kv`].
+///
+/// This is a separate enum from [`MsgPayload`] rather than more variants on
+/// it: both protocols use the `read`/`read_ok` tags for unrelated messages
+/// (a client's broadcast `read` vs. a storage service's key `read`), and
+/// `serde`'s internally-tagged derive matches a declared variant by tag name
+/// alone, so the two can't share one enum without one shadowing the other.
+/// A node only ever deserializes this as the payload of a matched `Runner::rpc`
+/// reply, never through the generic dispatch loop, so the tag clash is
+/// harmless as long as the two stay apart.
 #[derive(Debug, Serialize, Deserialize)]
-pub struct MsgBody<'b> {
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum KvPayload {
+    Read {
+        key: serde_json::Value,
+    },
+    ReadOk {
+        value: serde_json::Value,
+    },
+    Write {
+        key: serde_json::Value,
+        value: serde_json::Value,
+    },
+    WriteOk,
+    Cas {
+        key: serde_json::Value,
+        from: serde_json::Value,
+        to: serde_json::Value,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        create_if_not_exists: Option<bool>,
+    },
+    CasOk,
+    Error {
+        code: ErrorCode,
+        text: String,
+    },
+}
+
+/// A Maelstrom protocol error code, carried as a bare JSON integer rather
+/// than a string.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize_repr, Deserialize_repr)]
+#[repr(u64)]
+pub enum ErrorCode {
+    Timeout = 0,
+    NodeNotFound = 1,
+    NotSupported = 10,
+    TemporarilyUnavailable = 11,
+    MalformedRequest = 12,
+    Crash = 13,
+    Abort = 14,
+    KeyDoesNotExist = 20,
+    KeyAlreadyExists = 21,
+    PreconditionFailed = 22,
+    TxnConflict = 30,
+}
+
+impl ErrorCode {
+    /// True if retrying the request cannot change the outcome.
+    pub fn is_definite(&self) -> bool {
+        matches!(
+            self,
+            ErrorCode::MalformedRequest
+                | ErrorCode::NotSupported
+                | ErrorCode::KeyDoesNotExist
+                | ErrorCode::KeyAlreadyExists
+                | ErrorCode::PreconditionFailed
+        )
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MsgBody {
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub(crate) msg_id: Option<usize>,
+    pub msg_id: Option<usize>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub(crate) in_reply_to: Option<usize>,
-    #[serde(borrow, flatten)]
-    pub(crate) payload: MsgPayload<'b>,
+    pub in_reply_to: Option<usize>,
+    #[serde(flatten)]
+    pub payload: MsgPayload,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
-pub struct MsgEnvelope<'e> {
-    pub(crate) src: EntityId,
-    pub(crate) dest: EntityId,
-    #[serde(borrow)]
-    pub(crate) body: MsgBody<'e>,
+pub struct MsgEnvelope {
+    pub src: EntityId,
+    pub dest: EntityId,
+    pub body: MsgBody,
 }
 
-impl MsgEnvelope<'_> {
-    pub fn reply<'r>(&self, msg_id: usize, payload: MsgPayload<'r>) -> MsgEnvelope<'r> {
+impl MsgEnvelope {
+    pub fn reply(&self, msg_id: usize, payload: MsgPayload) -> MsgEnvelope {
         MsgEnvelope {
-            src: self.dest,
-            dest: self.src,
+            src: self.dest.clone(),
+            dest: self.src.clone(),
             body: MsgBody {
                 msg_id: Some(msg_id),
                 in_reply_to: self.body.msg_id,
@@ -141,7 +229,7 @@ mod tests {
 
     #[test]
     fn test_deserialize_init_request() {
-        let envelope = serde_json::from_str::<MsgEnvelope<'_>>(
+        let envelope = serde_json::from_str::<MsgEnvelope>(
             r#"{
             "src": "c1",
             "dest": "n1",
@@ -159,7 +247,7 @@ mod tests {
 
     #[test]
     fn test_deserialize_init_response() {
-        let envelope = serde_json::from_str::<MsgEnvelope<'_>>(
+        let envelope = serde_json::from_str::<MsgEnvelope>(
             r#"{
             "src": "n1",
             "dest": "c1",
@@ -175,7 +263,7 @@ mod tests {
 
     #[test]
     fn test_deserialize_echo_request() {
-        let envelope = serde_json::from_str::<MsgEnvelope<'_>>(
+        let envelope = serde_json::from_str::<MsgEnvelope>(
             r#"{
             "src": "c1",
             "dest": "n1",
@@ -192,7 +280,7 @@ mod tests {
 
     #[test]
     fn test_deserialize_echo_response() {
-        let envelope = serde_json::from_str::<MsgEnvelope<'_>>(
+        let envelope = serde_json::from_str::<MsgEnvelope>(
             r#"{
             "src": "n1",
             "dest": "c1",
@@ -207,4 +295,83 @@ mod tests {
 
         assert!(envelope.is_ok());
     }
+
+    #[test]
+    fn test_entity_id_service_fallback() {
+        assert_eq!("seq-kv".parse(), Ok(EntityId::Service("seq-kv".to_string())));
+        assert_eq!("n1".parse(), Ok(EntityId::Node(1)));
+        assert_eq!("c1".parse(), Ok(EntityId::Client(1)));
+    }
+
+    #[test]
+    fn test_error_code_round_trips_as_bare_number() {
+        let error = MsgPayload::Error {
+            code: ErrorCode::KeyDoesNotExist,
+            text: "not found".to_string(),
+        };
+        let serialized = serde_json::to_value(&error).unwrap();
+        assert_eq!(serialized["code"], 20);
+
+        let deserialized: MsgPayload = serde_json::from_value(serialized).unwrap();
+        assert!(matches!(
+            deserialized,
+            MsgPayload::Error {
+                code: ErrorCode::KeyDoesNotExist,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_error_code_is_definite() {
+        assert!(ErrorCode::MalformedRequest.is_definite());
+        assert!(ErrorCode::KeyDoesNotExist.is_definite());
+        assert!(!ErrorCode::Timeout.is_definite());
+        assert!(!ErrorCode::TemporarilyUnavailable.is_definite());
+    }
+
+    #[test]
+    fn test_deserialize_topology_request() {
+        let envelope = serde_json::from_str::<MsgEnvelope>(
+            r#"{
+            "src": "c1",
+            "dest": "n1",
+            "body": {
+                "type": "topology",
+                "msg_id": 1,
+                "topology": {"n1": ["n2", "n3"]}
+            }
+        }"#,
+        );
+
+        assert!(envelope.is_ok());
+    }
+
+    #[test]
+    fn test_deserialize_broadcast_read_request() {
+        let envelope = serde_json::from_str::<MsgEnvelope>(
+            r#"{
+            "src": "c1",
+            "dest": "n1",
+            "body": {
+                "type": "read",
+                "msg_id": 1
+            }
+        }"#,
+        );
+
+        assert!(matches!(envelope.unwrap().body.payload, MsgPayload::Read));
+    }
+
+    #[test]
+    fn test_deserialize_kv_read_ok() {
+        let payload = serde_json::from_str::<KvPayload>(
+            r#"{
+            "type": "read_ok",
+            "value": 42
+        }"#,
+        );
+
+        assert!(matches!(payload.unwrap(), KvPayload::ReadOk { value } if value == 42));
+    }
 }
@@ -0,0 +1,324 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use serde::{Serialize, de::DeserializeOwned};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::sync::{Mutex, oneshot};
+use tokio::task::JoinSet;
+
+use crate::message::{EntityId, ErrorCode, MsgBody, MsgEnvelope, MsgPayload};
+
+/// How often a node with no pending input gets an `on_tick` call, e.g. to
+/// retry un-acked gossip.
+const TICK_INTERVAL: Duration = Duration::from_millis(300);
+
+/// A Maelstrom workload implemented as a small state machine driven by a [`Runner`].
+///
+/// Implementors hold whatever state the workload needs (counters, seen sets,
+/// RNGs, ...) and react to inbound messages through `handle`. Everything
+/// related to the stdin/stdout protocol, `msg_id` bookkeeping, and node
+/// identity lives on `Runner` instead, so a `Node` only ever deals in
+/// messages. Each inbound message is handled on its own spawned task, so a
+/// `handle` call that `.await`s an RPC no longer has to block `Runner::run`
+/// from reading the next line off stdin (the original deadlock this was
+/// built to fix). State access is still a single `Mutex<N>`, though, so two
+/// inbound messages to the *same* node still run one at a time, including
+/// waiting out each other's RPC round-trips — this buys non-blocking stdin,
+/// not per-node concurrency.
+#[async_trait::async_trait]
+pub trait Node: Send {
+    /// Called once, after `init`/`init_ok` has been handled, with the node's
+    /// id and peers already available on `runner`. The default does nothing.
+    async fn on_init(&mut self, runner: &Runner<Self>)
+    where
+        Self: Sized,
+    {
+        let _ = runner;
+    }
+
+    /// Called for every non-`init` message addressed to this node that isn't
+    /// a reply to an outstanding `Runner::rpc` call.
+    async fn handle(&mut self, runner: &Runner<Self>, req: MsgEnvelope)
+    where
+        Self: Sized;
+
+    /// Called whenever roughly `TICK_INTERVAL` passes with no message to
+    /// handle, e.g. so a node can retry un-acked sends. The default does
+    /// nothing.
+    async fn on_tick(&mut self, runner: &Runner<Self>)
+    where
+        Self: Sized,
+    {
+        let _ = runner;
+    }
+}
+
+/// Owns the stdin/stdout event loop for a single [`Node`]: it parses incoming
+/// `MsgEnvelope`s, handles `init` itself, and dispatches everything else to
+/// `Node::handle` on its own task. It also tracks this node's identity, the
+/// outgoing `msg_id` counter, and in-flight `rpc` calls so a `Node`
+/// implementation never has to.
+pub struct Runner<N> {
+    node: Mutex<N>,
+    node_id: RwLock<Option<EntityId>>,
+    node_ids: RwLock<Vec<EntityId>>,
+    next_msg_id: AtomicUsize,
+    stdout: Mutex<tokio::io::Stdout>,
+    /// `msg_id`s an in-flight `rpc` call is waiting on, and where to deliver
+    /// the matching reply's raw payload. Kept as `serde_json::Value` rather
+    /// than `MsgPayload` so `rpc` can be generic over reply protocols (e.g.
+    /// the kv client's `KvPayload`) that don't share `MsgPayload`'s tag
+    /// namespace; see `KvPayload`'s doc comment for why they can't.
+    waiting: Mutex<HashMap<usize, oneshot::Sender<serde_json::Value>>>,
+}
+
+impl<N: Node + 'static> Runner<N> {
+    pub fn new(node: N) -> Arc<Self> {
+        Arc::new(Runner {
+            node: Mutex::new(node),
+            node_id: RwLock::new(None),
+            node_ids: RwLock::new(Vec::new()),
+            next_msg_id: AtomicUsize::new(1),
+            stdout: Mutex::new(tokio::io::stdout()),
+            waiting: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// This node's own id, as assigned by Maelstrom's `init` message.
+    ///
+    /// # Panics
+    /// Panics if called before `init` has been received.
+    pub fn node_id(&self) -> EntityId {
+        self.node_id
+            .read()
+            .unwrap()
+            .clone()
+            .expect("node_id requested before init was handled")
+    }
+
+    /// All node ids in the cluster, as assigned by Maelstrom's `init` message.
+    pub fn node_ids(&self) -> Vec<EntityId> {
+        self.node_ids.read().unwrap().clone()
+    }
+
+    /// Allocates the next outgoing `msg_id`.
+    pub fn next_msg_id(&self) -> usize {
+        self.next_msg_id.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// Replies to `req`, filling in `src`/`dest`/`in_reply_to` automatically.
+    pub async fn reply(&self, req: &MsgEnvelope, payload: MsgPayload) -> std::io::Result<()> {
+        let response = req.reply(self.next_msg_id(), payload);
+        self.write(&response).await
+    }
+
+    /// Sends an unsolicited message to `dest`, e.g. to gossip a value to a
+    /// peer. Returns the `msg_id` the message was sent under, so the caller
+    /// can correlate a later reply or ack against it.
+    pub async fn send(&self, dest: EntityId, payload: MsgPayload) -> std::io::Result<usize> {
+        let msg_id = self.next_msg_id();
+        self.write(&MsgEnvelope {
+            src: self.node_id(),
+            dest,
+            body: MsgBody {
+                msg_id: Some(msg_id),
+                in_reply_to: None,
+                payload,
+            },
+        })
+        .await?;
+        Ok(msg_id)
+    }
+
+    /// Sends `payload` to `dest` and awaits a reply with a matching
+    /// `in_reply_to`, or `timeout` elapsing.
+    ///
+    /// Generic over both the request payload `P` and the reply payload `R`
+    /// so this can drive `MsgPayload` RPCs as well as the kv client's
+    /// separate `KvPayload` wire protocol (see `KvPayload`'s doc comment):
+    /// a reply is routed here straight off `msg_id`, before it's ever
+    /// interpreted as one protocol or the other.
+    pub async fn rpc<P, R>(&self, dest: EntityId, payload: P, timeout: Duration) -> Result<R, ErrorCode>
+    where
+        P: Serialize,
+        R: DeserializeOwned,
+    {
+        let (tx, rx) = oneshot::channel();
+        let msg_id = self.next_msg_id();
+        self.waiting.lock().await.insert(msg_id, tx);
+
+        self.write_payload(&dest, msg_id, &payload)
+            .await
+            .expect("failed to write rpc request");
+
+        match tokio::time::timeout(timeout, rx).await {
+            Ok(Ok(value)) => Ok(serde_json::from_value(value).expect("malformed rpc reply")),
+            Ok(Err(_)) | Err(_) => {
+                self.waiting.lock().await.remove(&msg_id);
+                Err(ErrorCode::Timeout)
+            }
+        }
+    }
+
+    async fn write(&self, envelope: &MsgEnvelope) -> std::io::Result<()> {
+        let mut serialized = serde_json::to_string(envelope)?;
+        serialized.push('\n');
+        self.write_line(&serialized).await
+    }
+
+    /// Serializes and sends an envelope whose payload isn't `MsgPayload`,
+    /// e.g. a `KvPayload` request to a storage service.
+    async fn write_payload<P: Serialize>(&self, dest: &EntityId, msg_id: usize, payload: &P) -> std::io::Result<()> {
+        #[derive(Serialize)]
+        struct Envelope<'a, P> {
+            src: EntityId,
+            dest: &'a EntityId,
+            body: Body<'a, P>,
+        }
+        #[derive(Serialize)]
+        struct Body<'a, P> {
+            msg_id: usize,
+            #[serde(flatten)]
+            payload: &'a P,
+        }
+
+        let mut serialized = serde_json::to_string(&Envelope {
+            src: self.node_id(),
+            dest,
+            body: Body { msg_id, payload },
+        })?;
+        serialized.push('\n');
+        self.write_line(&serialized).await
+    }
+
+    /// Writes an already newline-terminated line to stdout under the shared lock.
+    async fn write_line(&self, line: &str) -> std::io::Result<()> {
+        let mut stdout = self.stdout.lock().await;
+        stdout.write_all(line.as_bytes()).await?;
+        stdout.flush().await
+    }
+
+    /// Handles `init` inline, then hands everything else to `Node::handle`.
+    async fn dispatch(self: &Arc<Self>, parsed: MsgEnvelope) {
+        if let MsgPayload::Init { node_id, node_ids } = &parsed.body.payload {
+            *self.node_id.write().unwrap() = Some(node_id.clone());
+            *self.node_ids.write().unwrap() = node_ids.clone();
+            if let Err(e) = self.reply(&parsed, MsgPayload::InitOk).await {
+                eprintln!("failed to write reply: {e}");
+            }
+            self.node.lock().await.on_init(self).await;
+        } else {
+            self.node.lock().await.handle(self, parsed).await;
+        }
+    }
+
+    /// Runs the event loop until stdin is closed, calling `Node::on_tick`
+    /// whenever `TICK_INTERVAL` passes with nothing to read. Every message is
+    /// dispatched on its own task so a slow `handle` call never blocks
+    /// reading the next line — it does not make concurrent `handle` calls
+    /// run concurrently, since they still share one `Mutex<N>`. `run` waits
+    /// for every spawned task to finish before returning, so a reply or RPC
+    /// still in flight when stdin closes isn't aborted mid-write.
+    pub async fn run(self: Arc<Self>) -> Result<(), Box<dyn std::error::Error>> {
+        let mut lines = BufReader::new(tokio::io::stdin()).lines();
+        let mut tasks = JoinSet::new();
+
+        loop {
+            let line = tokio::select! {
+                line = lines.next_line() => line?,
+                _ = tokio::time::sleep(TICK_INTERVAL) => {
+                    let runner = Arc::clone(&self);
+                    tasks.spawn(async move { runner.node.lock().await.on_tick(&runner).await; });
+                    continue;
+                }
+            };
+
+            let Some(line) = line else {
+                eprintln!("Detected EOF, exiting.");
+                break;
+            };
+            eprintln!("Received request ({} bytes): {}", line.len(), line);
+
+            let raw = match serde_json::from_str::<RawEnvelope>(&line) {
+                Ok(raw) => raw,
+                Err(e) => {
+                    eprintln!("failed to parse message: {e}");
+                    continue;
+                }
+            };
+
+            if let Some(id) = raw.body.in_reply_to {
+                let mut waiting = self.waiting.lock().await;
+                if let Some(tx) = waiting.remove(&id) {
+                    drop(waiting);
+                    let _ = tx.send(raw.body.payload);
+                    continue;
+                }
+            }
+
+            let payload = match serde_json::from_value::<MsgPayload>(raw.body.payload) {
+                Ok(payload) => payload,
+                Err(e) => {
+                    eprintln!("failed to parse payload: {e}");
+                    let unsupported = MsgEnvelope {
+                        src: raw.dest,
+                        dest: raw.src,
+                        body: MsgBody {
+                            msg_id: Some(self.next_msg_id()),
+                            in_reply_to: raw.body.msg_id,
+                            payload: MsgPayload::Error {
+                                code: ErrorCode::NotSupported,
+                                text: format!("unrecognized message type: {e}"),
+                            },
+                        },
+                    };
+                    if let Err(e) = self.write(&unsupported).await {
+                        eprintln!("failed to write reply: {e}");
+                    }
+                    continue;
+                }
+            };
+            let parsed = MsgEnvelope {
+                src: raw.src,
+                dest: raw.dest,
+                body: MsgBody {
+                    msg_id: raw.body.msg_id,
+                    in_reply_to: raw.body.in_reply_to,
+                    payload,
+                },
+            };
+
+            let runner = Arc::clone(&self);
+            tasks.spawn(async move { runner.dispatch(parsed).await });
+        }
+
+        while tasks.join_next().await.is_some() {}
+        Ok(())
+    }
+}
+
+/// An inbound envelope parsed just far enough to route it: `src`/`dest`/
+/// `msg_id`/`in_reply_to` are typed as usual, but the payload is left as a
+/// raw [`serde_json::Value`]. This lets `run` decide whether a message is a
+/// reply to a pending `rpc` call (and so belongs to whatever payload type
+/// the caller of `rpc` expects, e.g. `KvPayload`) before committing to
+/// `MsgPayload`, which is the only payload type the rest of the runtime
+/// (`Node::handle`, `init`) ever deals in.
+#[derive(serde::Deserialize)]
+struct RawEnvelope {
+    src: EntityId,
+    dest: EntityId,
+    body: RawBody,
+}
+
+#[derive(serde::Deserialize)]
+struct RawBody {
+    #[serde(default)]
+    msg_id: Option<usize>,
+    #[serde(default)]
+    in_reply_to: Option<usize>,
+    #[serde(flatten)]
+    payload: serde_json::Value,
+}